@@ -0,0 +1,50 @@
+// Benchmarks parsing the saved console pages, with the transports page padded to a busy
+// router's peer list. Run with `cargo bench --bench generate_metrics`.
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+// The exporter is a binary crate, so its source is compiled into the benchmark directly
+#[allow(dead_code)]
+#[path = "../src/main.rs"]
+mod exporter;
+
+use exporter::{AppState, ParserBackend, ScrapeOptions};
+
+const MAIN_PAGE: &str = include_str!("../tests/fixtures/main.html");
+const NETDB_PAGE: &str = include_str!("../tests/fixtures/netdb.html");
+const TRANSPORTS_PAGE: &str = include_str!("../tests/fixtures/transports.html");
+
+fn generate_metrics(c: &mut Criterion) {
+    let state = AppState::new(
+        reqwest::Client::new(),
+        "http://127.0.0.1:7070/".to_string(),
+        "?page={page}".to_string(),
+        ParserBackend::Regex,
+        ScrapeOptions::default(),
+        Arc::new(AtomicU64::new(0)),
+        None,
+    );
+    let peer = "<tr><td>NTCP2</td><td class='version'>0.9.61</td><td class='impl'>i2pd</td></tr>\n";
+    let transports =
+        TRANSPORTS_PAGE.replace("<tbody>\n", &format!("<tbody>\n{}", peer.repeat(5000)));
+    let subpages = HashMap::from([
+        ("netdb", NETDB_PAGE.to_string()),
+        ("transports", transports),
+    ]);
+
+    c.bench_function("generate_metrics", |b| {
+        b.iter(|| state.generate_metrics(black_box(MAIN_PAGE), black_box(&subpages)))
+    });
+
+    // The main page alone, where the single-pass field parser does its work
+    let no_subpages = HashMap::new();
+    c.bench_function("main_page_only", |b| {
+        b.iter(|| state.generate_metrics(black_box(MAIN_PAGE), black_box(&no_subpages)))
+    });
+}
+
+criterion_group!(benches, generate_metrics);
+criterion_main!(benches);
@@ -0,0 +1,138 @@
+// DOM-based parsers for the table sections of the console (`PARSER_BACKEND=dom`).
+// Selecting by table class survives whitespace and attribute changes that break the
+// regex parsers. Output types match the regex parsers in main.rs.
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use scraper::{CaseSensitivity::CaseSensitive, ElementRef, Html, Selector};
+
+// Headings and extaddr tables in document order, to find the table under the heading
+static EXTADDR_SECTION_SEL: Lazy<Selector> =
+    Lazy::new(|| Selector::parse("b, table.extaddr").unwrap());
+static ROW_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse("tr").unwrap());
+static SERVICES_ROW_SEL: Lazy<Selector> =
+    Lazy::new(|| Selector::parse("table.services tr").unwrap());
+static CELL_SEL: Lazy<Selector> = Lazy::new(|| Selector::parse("td").unwrap());
+
+// Collects the trimmed text of an element
+fn cell_text(cell: &ElementRef) -> String {
+    cell.text().collect::<String>().trim().to_string()
+}
+
+// Parse external addresses from the rows of the first `table.extaddr` after the
+// "Our external address:" heading, like the regex parser: (protocol, address)
+pub fn parse_external_addresses(html: &str) -> Vec<(String, String)> {
+    let document = Html::parse_document(html);
+    let mut addresses = Vec::new();
+
+    let table = document
+        .select(&EXTADDR_SECTION_SEL)
+        .skip_while(|element| cell_text(element) != "Our external address:")
+        .find(|element| element.value().name() == "table");
+    let Some(table) = table else {
+        return addresses;
+    };
+    for row in table.select(&ROW_SEL) {
+        let cells: Vec<ElementRef> = row.select(&CELL_SEL).collect();
+        if let [protocol, address] = cells.as_slice() {
+            addresses.push((cell_text(protocol), cell_text(address)));
+        }
+    }
+
+    addresses
+}
+
+// Parse service statuses from `table.services` rows: service name -> enabled
+pub fn parse_service_statuses(html: &str) -> HashMap<String, bool> {
+    let document = Html::parse_document(html);
+    let mut services = HashMap::new();
+
+    for row in document.select(&SERVICES_ROW_SEL) {
+        let cells: Vec<ElementRef> = row.select(&CELL_SEL).collect();
+        if let [service, status] = cells.as_slice() {
+            let element = status.value();
+            let is_enabled = if element.has_class("enabled", CaseSensitive) {
+                true
+            } else if element.has_class("disabled", CaseSensitive) {
+                false
+            } else {
+                continue;
+            };
+            services.insert(super::service_label(&cell_text(service)), is_enabled);
+        }
+    }
+
+    services
+}
+
+#[cfg(all(test, feature = "dom-parser"))]
+mod tests {
+    use std::sync::atomic::AtomicU64;
+    use std::sync::Arc;
+
+    use crate::{AppState, ParserBackend, ScrapeOptions};
+
+    const MAIN_PAGE: &str = include_str!("../tests/fixtures/main.html");
+    const SECOND_EXTADDR_PAGE: &str = include_str!("../tests/fixtures/extaddr_second_table.html");
+
+    fn state(parser_backend: ParserBackend) -> AppState {
+        AppState::new(
+            reqwest::Client::new(),
+            "http://127.0.0.1:7070/".to_string(),
+            "?page={page}".to_string(),
+            parser_backend,
+            ScrapeOptions::default(),
+            Arc::new(AtomicU64::new(0)),
+            None,
+        )
+    }
+
+    // Service rows come out of a HashMap, so compare expositions line by line
+    fn sorted_lines(metrics: &str) -> Vec<&str> {
+        let mut lines: Vec<&str> = metrics.lines().collect();
+        lines.sort_unstable();
+        lines
+    }
+
+    #[test]
+    fn dom_backend_matches_regex_backend() {
+        let regex = state(ParserBackend::Regex);
+        let dom = state(ParserBackend::Dom);
+
+        for page in [MAIN_PAGE, SECOND_EXTADDR_PAGE] {
+            assert_eq!(
+                dom.parse_external_addresses(page),
+                regex.parse_external_addresses(page)
+            );
+            assert_eq!(
+                dom.parse_service_statuses(page),
+                regex.parse_service_statuses(page)
+            );
+            assert_eq!(
+                sorted_lines(&dom.generate_metrics(page, &Default::default())),
+                sorted_lines(&regex.generate_metrics(page, &Default::default()))
+            );
+        }
+        assert_eq!(
+            super::parse_external_addresses(SECOND_EXTADDR_PAGE),
+            vec![("SSU2".to_string(), "5.6.7.8:4567".to_string())]
+        );
+    }
+
+    #[test]
+    fn parses_saved_console_tables() {
+        assert_eq!(
+            super::parse_external_addresses(MAIN_PAGE),
+            vec![
+                ("NTCP2".to_string(), "1.2.3.4:12345".to_string()),
+                ("SSU2".to_string(), "1.2.3.4:12345".to_string()),
+            ]
+        );
+        let services = super::parse_service_statuses(MAIN_PAGE);
+        assert_eq!(services.len(), 3);
+        assert_eq!(services.get("http_proxy"), Some(&true));
+        assert_eq!(services.get("socks_proxy"), Some(&true));
+        assert_eq!(services.get("bob"), Some(&false));
+    }
+}
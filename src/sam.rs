@@ -0,0 +1,211 @@
+// Serves /metrics over an I2P streaming destination through i2pd's SAM v3 bridge
+// (`SAM_LISTEN=true`), so Prometheus can scrape from inside I2P without clearnet exposure.
+// Only the bits of SAM needed for a STREAM session and incoming connections are spoken.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, error, info, warn};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use super::AppState;
+
+// Pause before recreating the SAM session after the bridge dropped it
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+// Largest request head accepted from an I2P client
+const MAX_REQUEST_BYTES: usize = 8192;
+
+// Signature type for new destinations; the SAM default is the obsolete DSA_SHA1
+const SIGNATURE_TYPE: &str = "EdDSA_SHA512_Ed25519";
+
+type SamStream = BufReader<TcpStream>;
+
+// Opens a bridge connection and completes the HELLO handshake
+async fn connect(sam_address: &str) -> std::io::Result<SamStream> {
+    let mut stream = BufReader::new(TcpStream::connect(sam_address).await?);
+    command(&mut stream, "HELLO VERSION MIN=3.1 MAX=3.3").await?;
+    Ok(stream)
+}
+
+// Sends one SAM command and returns its reply line
+async fn exchange(stream: &mut SamStream, line: &str) -> std::io::Result<String> {
+    stream
+        .get_mut()
+        .write_all(format!("{}\n", line).as_bytes())
+        .await?;
+    let mut reply = String::new();
+    if stream.read_line(&mut reply).await? == 0 {
+        return Err(std::io::Error::other("SAM bridge closed the connection"));
+    }
+    Ok(reply.trim_end().to_string())
+}
+
+// Sends one SAM command and returns its reply line, failing unless RESULT=OK
+async fn command(stream: &mut SamStream, line: &str) -> std::io::Result<String> {
+    let reply = exchange(stream, line).await?;
+    if !reply.split(' ').any(|field| field == "RESULT=OK") {
+        return Err(std::io::Error::other(format!(
+            "SAM bridge replied: {}",
+            reply
+        )));
+    }
+    Ok(reply)
+}
+
+// Reads the destination's private key from `path`, or has the bridge generate a new one
+// and saves it there, so the .b32.i2p address stays the same across restarts
+pub(crate) async fn load_or_generate_key(
+    sam_address: &str,
+    path: &Path,
+) -> std::io::Result<String> {
+    match std::fs::read_to_string(path) {
+        Ok(key) => return Ok(key.trim().to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    let mut stream = connect(sam_address).await?;
+    let reply = exchange(
+        &mut stream,
+        &format!("DEST GENERATE SIGNATURE_TYPE={}", SIGNATURE_TYPE),
+    )
+    .await?;
+    let key = reply
+        .split(' ')
+        .find_map(|field| field.strip_prefix("PRIV="))
+        .ok_or_else(|| std::io::Error::other(format!("SAM bridge replied: {}", reply)))?;
+
+    // The key is the destination's identity, so keep it private to the exporter's user
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    std::io::Write::write_all(&mut options.open(path)?, format!("{}\n", key).as_bytes())?;
+    info!("Saved a new SAM destination key to {}", path.display());
+    Ok(key.to_string())
+}
+
+// Keeps a SAM session alive and serves incoming streams, recreating the session if the
+// bridge goes away
+pub async fn serve(
+    state: Arc<AppState>,
+    sam_address: String,
+    session_id: String,
+    key_file: Option<PathBuf>,
+    content_type: String,
+) {
+    loop {
+        let session = run_session(
+            &state,
+            &sam_address,
+            &session_id,
+            key_file.as_deref(),
+            &content_type,
+        );
+        if let Err(e) = session.await {
+            error!("SAM session '{}' failed: {}", session_id, e);
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn run_session(
+    state: &Arc<AppState>,
+    sam_address: &str,
+    session_id: &str,
+    key_file: Option<&Path>,
+    content_type: &str,
+) -> std::io::Result<()> {
+    // Without a key file the bridge makes up a new destination for every session
+    let destination = match key_file {
+        Some(path) => load_or_generate_key(sam_address, path).await?,
+        None => "TRANSIENT".to_string(),
+    };
+
+    // The session lives as long as this control connection stays open
+    let mut control = connect(sam_address).await?;
+    command(
+        &mut control,
+        &format!(
+            "SESSION CREATE STYLE=STREAM ID={} DESTINATION={} SIGNATURE_TYPE={}",
+            session_id, destination, SIGNATURE_TYPE
+        ),
+    )
+    .await?;
+    let lookup = command(&mut control, "NAMING LOOKUP NAME=ME").await?;
+    let destination = lookup
+        .split(' ')
+        .find_map(|field| field.strip_prefix("VALUE="))
+        .unwrap_or("unknown");
+    info!(
+        "Serving /metrics over I2P via SAM at destination {}",
+        destination
+    );
+
+    loop {
+        let mut stream = connect(sam_address).await?;
+        command(&mut stream, &format!("STREAM ACCEPT ID={}", session_id)).await?;
+
+        // The bridge announces an accepted connection with the peer's destination
+        let mut peer = String::new();
+        if stream.read_line(&mut peer).await? == 0 {
+            return Err(std::io::Error::other("SAM bridge closed the session"));
+        }
+        debug!("Accepted I2P connection from {}", peer.trim_end());
+
+        let state = state.clone();
+        let content_type = content_type.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = handle_request(&state, stream, &content_type).await {
+                warn!("Failed to answer I2P metrics request: {}", e);
+            }
+        });
+    }
+}
+
+// Answers a single HTTP/1.x request on an accepted stream, then closes it
+async fn handle_request(
+    state: &AppState,
+    mut stream: SamStream,
+    content_type: &str,
+) -> std::io::Result<()> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 || head.len() + n > MAX_REQUEST_BYTES {
+            return Ok(());
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    let request_line = String::from_utf8_lossy(&head);
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let health = query.split('&').any(|pair| pair == "mode=health");
+
+    let (status, body) = match (method, path) {
+        ("GET", "/metrics") if health => ("200 OK", state.health_metrics().await),
+        ("GET", "/metrics") => ("200 OK", state.serve_metrics().await),
+        (_, "/metrics") => ("405 Method Not Allowed", "Method Not Allowed".to_string()),
+        _ => ("404 Not Found", "Not Found".to_string()),
+    };
+    let allow = if status.starts_with("405") {
+        "Allow: GET\r\n"
+    } else {
+        ""
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\n{}Content-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        allow,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.get_mut().write_all(response.as_bytes()).await?;
+    stream.get_mut().shutdown().await
+}